@@ -0,0 +1,37 @@
+use crate::interface::{EditArgs, NHRunnable};
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use std::process::Command;
+
+impl NHRunnable for EditArgs {
+    fn run(&self) -> Result<()> {
+        let dir = self.flakeref.resolve_local_path()?;
+        let editor = self
+            .editor
+            .clone()
+            .unwrap_or_else(|| "vi".into())
+            .to_string_lossy()
+            .into_owned();
+
+        // $EDITOR/--editor commonly carries its own arguments, e.g.
+        // `EDITOR="code --wait"` or `EDITOR="emacsclient -nw"`.
+        let mut parts = editor.split_whitespace();
+        let program = parts
+            .next()
+            .ok_or_else(|| eyre!("editor command `{editor}` is empty"))?;
+
+        let status = Command::new(program)
+            .args(parts)
+            .arg(&dir)
+            .status()
+            .wrap_err_with(|| format!("failed to launch editor `{editor}`"))?;
+
+        if !status.success() {
+            bail!("editor `{editor}` exited with {status}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use crate::interface::CommonRebuildArgs;
+use color_eyre::{eyre::eyre, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+/// How many `nix copy` / activation pairs run at once. Bounded so deploying
+/// to a large fleet doesn't open hundreds of SSH connections simultaneously.
+const MAX_CONCURRENT_DEPLOYS: usize = 4;
+
+/// Outcome of copying and activating the closure on a single target host.
+pub struct HostResult {
+    pub host: String,
+    pub outcome: Result<(), String>,
+}
+
+impl CommonRebuildArgs {
+    /// Copy `out_path` to every `--target-host` and activate it there under
+    /// `elevation_program`. Runs up to [`MAX_CONCURRENT_DEPLOYS`] hosts
+    /// concurrently and never aborts early: every host gets a result so
+    /// callers can report per-host success/failure at the end.
+    pub fn deploy(&self, out_path: &Path, elevation_program: &str) -> Result<Vec<HostResult>> {
+        if self.target_host.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // `out_path` is typically the `--out-link` result symlink, not the
+        // store path itself. `nix copy` happily follows it locally, but the
+        // activation command runs *on the target host*, where only the
+        // resolved store path exists — so resolve it once up front.
+        let out_path = out_path
+            .canonicalize()
+            .map_err(|err| eyre!("failed to resolve {}: {err}", out_path.display()))?;
+
+        let queue = Arc::new(Mutex::new(self.target_host.clone()));
+        let (tx, rx) = mpsc::channel();
+        let worker_count = MAX_CONCURRENT_DEPLOYS.min(self.target_host.len());
+        let mut workers = Vec::with_capacity(worker_count);
+
+        for _ in 0..worker_count {
+            let queue = Arc::clone(&queue);
+            let tx = tx.clone();
+            let out_path = out_path.to_path_buf();
+            let elevation_program = elevation_program.to_owned();
+
+            workers.push(thread::spawn(move || loop {
+                let host = match queue.lock().unwrap().pop() {
+                    Some(host) => host,
+                    None => break,
+                };
+
+                let outcome = copy_and_activate(&out_path, &host, &elevation_program)
+                    .map_err(|err| format!("{err:#}"));
+                let _ = tx.send(HostResult { host, outcome });
+            }));
+        }
+        drop(tx);
+
+        let results = rx.into_iter().collect();
+        for worker in workers {
+            let _ = worker.join();
+        }
+
+        Ok(results)
+    }
+
+    /// Build `installable` into `out_link`, either locally or on
+    /// `--build-host` over SSH, returning the resolved store path.
+    pub fn build(&self, installable: &str, out_link: &Path) -> Result<PathBuf> {
+        match &self.build_host {
+            Some(host) => build_remote(installable, host, out_link),
+            None => build_local(installable, out_link),
+        }
+    }
+}
+
+fn build_local(installable: &str, out_link: &Path) -> Result<PathBuf> {
+    let status = Command::new("nix")
+        .arg("build")
+        .arg(installable)
+        .arg("--out-link")
+        .arg(out_link)
+        .status()
+        .map_err(|err| eyre!("failed to run `nix build`: {err}"))?;
+
+    if !status.success() {
+        return Err(eyre!("`nix build` exited with {status}"));
+    }
+
+    out_link
+        .canonicalize()
+        .map_err(|err| eyre!("failed to resolve {}: {err}", out_link.display()))
+}
+
+/// Build on `build_host` over SSH, then `nix copy` the result back so the
+/// caller has a local out-link to inspect/diff before any `--target-host`
+/// deploy, reusing the same control-connection convention as `deploy()`.
+fn build_remote(installable: &str, host: &str, out_link: &Path) -> Result<PathBuf> {
+    let ssh_opts = ssh_control_opts(host);
+    let remote_command = format!("nix build {installable} --print-out-paths --no-link");
+
+    let output = Command::new("ssh")
+        .args(ssh_opts.split_whitespace())
+        .arg(host)
+        .arg(remote_command)
+        .output()
+        .map_err(|err| eyre!("failed to build on {host}: {err}"))?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "remote `nix build` on {host} exited with {}",
+            output.status
+        ));
+    }
+
+    let store_path = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if store_path.is_empty() {
+        return Err(eyre!("remote `nix build` on {host} produced no output path"));
+    }
+
+    let status = Command::new("nix")
+        .arg("copy")
+        .arg("--from")
+        .arg(format!("ssh://{host}"))
+        .env("NIX_SSHOPTS", &ssh_opts)
+        .arg(&store_path)
+        .status()
+        .map_err(|err| eyre!("failed to copy {store_path} back from {host}: {err}"))?;
+
+    if !status.success() {
+        return Err(eyre!("`nix copy --from {host}` exited with {status}"));
+    }
+
+    if out_link.symlink_metadata().is_ok() {
+        fs::remove_file(out_link)
+            .map_err(|err| eyre!("failed to replace {}: {err}", out_link.display()))?;
+    }
+    std::os::unix::fs::symlink(&store_path, out_link)
+        .map_err(|err| eyre!("failed to link {} to {store_path}: {err}", out_link.display()))?;
+
+    Ok(PathBuf::from(store_path))
+}
+
+/// Report per-host results to stdout/stderr after a `deploy()` run.
+pub fn report(results: &[HostResult]) {
+    for result in results {
+        match &result.outcome {
+            Ok(()) => println!("{}: ok", result.host),
+            Err(err) => eprintln!("{}: failed: {err}", result.host),
+        }
+    }
+}
+
+fn ssh_control_opts(host: &str) -> String {
+    format!(
+        "-o ControlMaster=auto -o ControlPath=/tmp/nh-deploy-{host} -o ControlPersist=30"
+    )
+}
+
+fn copy_and_activate(out_path: &Path, host: &str, elevation_program: &str) -> Result<()> {
+    // Reuse one SSH control connection for both the copy and the remote
+    // activation command instead of negotiating a new connection each time.
+    let ssh_opts = ssh_control_opts(host);
+
+    let status = Command::new("nix")
+        .arg("copy")
+        .arg("--to")
+        .arg(format!("ssh://{host}"))
+        .env("NIX_SSHOPTS", &ssh_opts)
+        .arg(out_path)
+        .status()
+        .map_err(|err| eyre!("failed to `nix copy` to {host}: {err}"))?;
+
+    if !status.success() {
+        return Err(eyre!("`nix copy` to {host} exited with {status}"));
+    }
+
+    let remote_command = format!(
+        "{elevation_program} {}/bin/switch-to-configuration switch",
+        out_path.display()
+    );
+
+    let status = Command::new("ssh")
+        .args(ssh_opts.split_whitespace())
+        .arg(host)
+        .arg(remote_command)
+        .status()
+        .map_err(|err| eyre!("failed to activate on {host}: {err}"))?;
+
+    if !status.success() {
+        return Err(eyre!("activation on {host} exited with {status}"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssh_control_opts_reuse_one_socket_per_host() {
+        let opts = ssh_control_opts("box");
+        assert!(opts.contains("ControlPath=/tmp/nh-deploy-box"));
+        assert!(opts.contains("ControlMaster=auto"));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::interface::{
+    GenerationsArgs, GenerationsDiffArgs, GenerationsMode, GenerationsRollbackArgs, NHRunnable,
+};
+use color_eyre::{
+    eyre::{bail, eyre, WrapErr},
+    Result,
+};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const DEFAULT_SYSTEM_PROFILE: &str = "/nix/var/nix/profiles/system";
+
+#[derive(Debug, Clone)]
+struct Generation {
+    number: u64,
+    path: PathBuf,
+    build_date: std::time::SystemTime,
+    current: bool,
+    closure_size: u64,
+}
+
+impl NHRunnable for GenerationsMode {
+    fn run(&self) -> Result<()> {
+        match self {
+            GenerationsMode::List(args) => list(args),
+            GenerationsMode::Diff(args) => diff(args),
+            GenerationsMode::Rollback(args) => rollback(args),
+        }
+    }
+}
+
+fn profile_path(args: &GenerationsArgs) -> PathBuf {
+    args.profile
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSTEM_PROFILE))
+}
+
+fn list(args: &GenerationsArgs) -> Result<()> {
+    let profile = profile_path(args);
+    let generations = list_generations(&profile)?;
+
+    println!(
+        "{:<6} {:<27} {:<8} {:>10}",
+        "GEN", "BUILD DATE", "CURRENT", "SIZE"
+    );
+    for generation in &generations {
+        println!(
+            "{:<6} {:<27} {:<8} {:>10}",
+            generation.number,
+            humantime::format_rfc3339_seconds(generation.build_date),
+            if generation.current { "yes" } else { "" },
+            format_size(generation.closure_size),
+        );
+    }
+
+    Ok(())
+}
+
+fn diff(args: &GenerationsDiffArgs) -> Result<()> {
+    let profile = profile_path(&args.common);
+    let generations = list_generations(&profile)?;
+
+    let from = find_generation(&generations, args.from)?;
+    let to = find_generation(&generations, args.to)?;
+
+    let mut parts = args.common.diff_provider.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| eyre!("diff provider `{}` is empty", args.common.diff_provider))?;
+
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&from.path)
+        .arg(&to.path)
+        .status()
+        .wrap_err_with(|| format!("failed to run diff provider `{}`", args.common.diff_provider))?;
+
+    if !status.success() {
+        bail!("diff provider exited with {status}");
+    }
+
+    Ok(())
+}
+
+fn rollback(args: &GenerationsRollbackArgs) -> Result<()> {
+    let profile = profile_path(&args.common);
+    let generations = list_generations(&profile)?;
+    let target = find_generation(&generations, args.generation)?;
+
+    if args.confirm.ask {
+        print!(
+            "Roll back {} to generation {}? [y/N] ",
+            profile.display(),
+            args.generation
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            bail!("rollback aborted");
+        }
+    }
+
+    if args.confirm.dry {
+        println!(
+            "Would switch {} to generation {} ({})",
+            profile.display(),
+            args.generation,
+            target.path.display()
+        );
+        return Ok(());
+    }
+
+    let elevation_program = elevation_program();
+
+    let status = Command::new(&elevation_program)
+        .arg("nix-env")
+        .arg("-p")
+        .arg(&profile)
+        .arg("--switch-generation")
+        .arg(args.generation.to_string())
+        .status()
+        .wrap_err_with(|| format!("failed to switch {} to a prior generation", profile.display()))?;
+
+    if !status.success() {
+        bail!(
+            "failed to switch {} to generation {}",
+            profile.display(),
+            args.generation
+        );
+    }
+
+    let activate = target.path.join("bin").join("switch-to-configuration");
+    if activate.exists() {
+        let status = Command::new(&elevation_program)
+            .arg(&activate)
+            .arg("switch")
+            .status()
+            .wrap_err_with(|| format!("failed to run {}", activate.display()))?;
+        if !status.success() {
+            bail!("activation script exited with {status}");
+        }
+    }
+
+    Ok(())
+}
+
+/// The privilege elevation program, matching `NHParser.elevation_program`'s
+/// `ELEVATION_PROGRAM` env source: `nh generations rollback` needs it for the
+/// same reason `deploy.rs` threads it through for remote activation — the
+/// system profile is root-owned.
+fn elevation_program() -> String {
+    std::env::var("ELEVATION_PROGRAM").unwrap_or_else(|_| "sudo".to_owned())
+}
+
+fn find_generation(generations: &[Generation], number: u64) -> Result<Generation> {
+    generations
+        .iter()
+        .find(|generation| generation.number == number)
+        .cloned()
+        .ok_or_else(|| eyre!("generation {number} not found"))
+}
+
+fn list_generations(profile: &Path) -> Result<Vec<Generation>> {
+    let dir = profile
+        .parent()
+        .ok_or_else(|| eyre!("profile {} has no parent directory", profile.display()))?;
+    let profile_name = profile
+        .file_name()
+        .ok_or_else(|| eyre!("profile {} has no file name", profile.display()))?
+        .to_string_lossy()
+        .into_owned();
+
+    // `profile` (e.g. `/nix/var/nix/profiles/system`) is itself a symlink to
+    // a `*-link` name, which is in turn a symlink to the store path, so a
+    // single `read_link` hop never matches a generation's resolved target.
+    let current_target = fs::canonicalize(profile).ok();
+    let prefix = format!("{profile_name}-");
+
+    let mut generations = Vec::new();
+    for entry in fs::read_dir(dir).wrap_err_with(|| format!("failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let Some(rest) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let Some(number_str) = rest.strip_suffix("-link") else {
+            continue;
+        };
+        let Ok(number) = number_str.parse::<u64>() else {
+            continue;
+        };
+
+        let link = dir.join(entry.file_name());
+        let target = fs::read_link(&link)
+            .wrap_err_with(|| format!("failed to read symlink {}", link.display()))?;
+        let build_date = fs::symlink_metadata(&link)?.modified()?;
+
+        generations.push(Generation {
+            number,
+            current: current_target.as_deref() == Some(target.as_path()),
+            closure_size: closure_size(&target).unwrap_or(0),
+            path: target,
+            build_date,
+        });
+    }
+
+    generations.sort_by_key(|generation| generation.number);
+    Ok(generations)
+}
+
+fn closure_size(store_path: &Path) -> Result<u64> {
+    let output = Command::new("nix")
+        .args(["path-info", "--closure-size", "--json"])
+        .arg(store_path)
+        .output()
+        .wrap_err("failed to run `nix path-info`")?;
+
+    if !output.status.success() {
+        bail!("`nix path-info` failed for {}", store_path.display());
+    }
+
+    let parsed: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .wrap_err("failed to parse `nix path-info` output")?;
+
+    Ok(parsed
+        .as_array()
+        .and_then(|entries| entries.first())
+        .and_then(|entry| entry.get("closureSize"))
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0))
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_size_picks_appropriate_unit() {
+        assert_eq!(format_size(512), "512.0 B");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn profile_symlink_needs_canonicalize_to_reach_store_path() {
+        let tmp = std::env::temp_dir().join(format!(
+            "nh-generations-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&tmp).unwrap();
+        let store_path = tmp.join("store-path");
+        fs::create_dir_all(&store_path).unwrap();
+
+        let link = tmp.join("system-1-link");
+        std::os::unix::fs::symlink(&store_path, &link).unwrap();
+        let profile = tmp.join("system");
+        std::os::unix::fs::symlink(&link, &profile).unwrap();
+
+        // One read_link hop lands on `system-1-link`, not the store path,
+        // which is why `list_generations` must canonicalize instead.
+        assert_eq!(fs::read_link(&profile).unwrap(), link);
+        assert_eq!(fs::canonicalize(&profile).unwrap(), store_path.canonicalize().unwrap());
+
+        fs::remove_dir_all(&tmp).unwrap();
+    }
+}
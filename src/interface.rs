@@ -1,7 +1,10 @@
 use ambassador::{delegatable_trait, Delegate};
 use anstyle::Style;
 use clap::{builder::Styles, Args, Parser, Subcommand};
-use color_eyre::Result;
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
 use std::{
     ffi::OsString,
     fmt::Display,
@@ -46,6 +49,39 @@ impl Deref for FlakeRef {
     }
 }
 
+impl FlakeRef {
+    /// Split a trailing `#attr` fragment off the flake reference, returning
+    /// the flake part and the attribute, if any.
+    pub fn split_fragment(&self) -> (&str, Option<&str>) {
+        match self.0.split_once('#') {
+            Some((flake, attr)) => (flake, Some(attr)),
+            None => (self.0.as_str(), None),
+        }
+    }
+
+    /// Resolve this flake reference to a local directory that can be handed
+    /// to an editor, erroring out for refs with no corresponding local file
+    /// (e.g. `github:`/`git+` refs).
+    pub fn resolve_local_path(&self) -> Result<PathBuf> {
+        let (flake, _attr) = self.split_fragment();
+
+        if flake.starts_with("github:") || flake.starts_with("git+") {
+            return Err(eyre!(
+                "cannot edit `{flake}`: it doesn't resolve to a local path"
+            ));
+        }
+
+        let path = if flake.is_empty() || flake == "." {
+            std::env::current_dir().wrap_err("failed to get current directory")?
+        } else {
+            PathBuf::from(flake)
+        };
+
+        path.canonicalize()
+            .wrap_err_with(|| format!("failed to resolve `{flake}` to a local path"))
+    }
+}
+
 fn make_style() -> Styles {
     Styles::plain().header(Style::new().bold()).literal(
         Style::new()
@@ -96,9 +132,23 @@ pub enum NHCommand {
     Home(HomeArgs),
     Search(SearchArgs),
     Clean(CleanProxy),
+    Edit(EditArgs),
+    Generations(GenerationsProxy),
     Completions(CompletionArgs),
 }
 
+#[derive(Args, Debug)]
+/// Open the resolved flake in $EDITOR
+pub struct EditArgs {
+    /// Flake reference to edit
+    #[arg(env = "FLAKE", value_hint = clap::ValueHint::DirPath)]
+    pub flakeref: FlakeRef,
+
+    /// Override the editor to use instead of $EDITOR
+    #[arg(long, short = 'E', env = "EDITOR")]
+    pub editor: Option<OsString>,
+}
+
 #[derive(Debug, Args)]
 pub struct CommonReplArgs {
     /// Flake reference to build
@@ -218,6 +268,14 @@ pub struct CommonRebuildArgs {
     /// Path to save the result link. Defaults to using a temporary directory.
     #[arg(long, short)]
     pub out_link: Option<PathBuf>,
+
+    /// Build the configuration on a remote host over SSH instead of locally
+    #[arg(long, value_hint = clap::ValueHint::Hostname)]
+    pub build_host: Option<String>,
+
+    /// Deploy the built closure to one or more remote hosts over SSH
+    #[arg(long, value_hint = clap::ValueHint::Hostname)]
+    pub target_host: Vec<String>,
 }
 
 #[derive(Args, Debug)]
@@ -237,6 +295,10 @@ pub struct SearchArgs {
     #[arg(short, long, env = "FLAKE", value_hint = clap::ValueHint::DirPath)]
     /// Flake to read what nixpkgs channels to search for
     pub flake: Option<FlakeRef>,
+
+    /// Print results as JSON instead of the human formatted listing
+    #[arg(long)]
+    pub json: bool,
 }
 
 // Needed a struct to have multiple sub-subcommands
@@ -258,6 +320,18 @@ pub enum CleanMode {
     Profile(CleanProfileArgs),
 }
 
+/// Shared `--dry`/`--ask` confirmation semantics for destructive subcommands
+#[derive(Args, Clone, Debug)]
+pub struct DryAskArgs {
+    /// Only print actions, without performing them
+    #[arg(long, short = 'n')]
+    pub dry: bool,
+
+    /// Ask for confirmation
+    #[arg(long, short)]
+    pub ask: bool,
+}
+
 #[derive(Args, Clone, Debug)]
 #[clap(verbatim_doc_comment)]
 /// Enhanced nix cleanup
@@ -297,6 +371,69 @@ pub struct CleanProfileArgs {
     pub profile: PathBuf,
 }
 
+// Needed a struct to have multiple sub-subcommands
+#[derive(Debug, Clone, Args, Delegate)]
+#[delegate(NHRunnable)]
+pub struct GenerationsProxy {
+    #[clap(subcommand)]
+    command: GenerationsMode,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+/// Inspect and manage system/home generations
+pub enum GenerationsMode {
+    /// List the generations of a profile, along with their closure size
+    List(GenerationsArgs),
+    /// Diff two generations of a profile using the configured diff provider
+    Diff(GenerationsDiffArgs),
+    /// Roll a profile back to a prior generation and re-run its switch-to-configuration
+    ///
+    /// A generation without a `bin/switch-to-configuration` script is switched to but not activated
+    Rollback(GenerationsRollbackArgs),
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GenerationsArgs {
+    /// Profile to inspect. Defaults to the system profile
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    pub profile: Option<PathBuf>,
+
+    /// Closure diff provider, used by `nh generations diff`
+    ///
+    /// Default is "nvd diff", but "nix store diff-closures" is also supported
+    #[arg(
+        long,
+        short = 'D',
+        env = "NH_DIFF_PROVIDER",
+        default_value = "nvd diff"
+    )]
+    pub diff_provider: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GenerationsDiffArgs {
+    #[command(flatten)]
+    pub common: GenerationsArgs,
+
+    /// First generation number to compare
+    pub from: u64,
+
+    /// Second generation number to compare
+    pub to: u64,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct GenerationsRollbackArgs {
+    #[command(flatten)]
+    pub common: GenerationsArgs,
+
+    /// Generation number to roll back to
+    pub generation: u64,
+
+    #[command(flatten)]
+    pub confirm: DryAskArgs,
+}
+
 #[derive(Debug, Args)]
 /// Home-manager functionality
 pub struct HomeArgs {
@@ -353,3 +490,42 @@ pub struct CompletionArgs {
     #[arg(long, short)]
     pub shell: clap_complete::Shell,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_fragment_strips_trailing_attr() {
+        let flakeref = FlakeRef::from(".#nixosConfigurations.box");
+        assert_eq!(
+            flakeref.split_fragment(),
+            (".", Some("nixosConfigurations.box"))
+        );
+    }
+
+    #[test]
+    fn split_fragment_without_attr() {
+        let flakeref = FlakeRef::from(".");
+        assert_eq!(flakeref.split_fragment(), (".", None));
+    }
+
+    #[test]
+    fn resolve_local_path_rejects_remote_refs() {
+        assert!(FlakeRef::from("github:foo/bar")
+            .resolve_local_path()
+            .is_err());
+        assert!(FlakeRef::from("git+https://example.com/foo")
+            .resolve_local_path()
+            .is_err());
+    }
+
+    #[test]
+    fn resolve_local_path_resolves_dot_to_cwd() {
+        let resolved = FlakeRef::from(".").resolve_local_path().unwrap();
+        assert_eq!(
+            resolved,
+            std::env::current_dir().unwrap().canonicalize().unwrap()
+        );
+    }
+}
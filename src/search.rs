@@ -0,0 +1,95 @@
+use crate::interface::{NHRunnable, SearchArgs};
+use color_eyre::{eyre::WrapErr, Result};
+use serde::Serialize;
+
+const DEFAULT_CHANNEL: &str = "nixos-unstable";
+
+/// A single package match, shaped for both the human listing and `--json`.
+#[derive(Debug, Serialize)]
+pub struct SearchEntry {
+    pub attr_name: String,
+    pub pname: String,
+    pub version: String,
+    pub description: String,
+    pub channel: String,
+}
+
+impl NHRunnable for SearchArgs {
+    fn run(&self) -> Result<()> {
+        let channel = self.channel.clone().unwrap_or_else(|| DEFAULT_CHANNEL.to_owned());
+        let entries = query(&self.query, self.limit, &channel)?;
+
+        if self.json {
+            // `--json` suppresses the decorated listing entirely: only valid
+            // JSON goes to stdout so it can be piped into `jq` or a script.
+            let json =
+                serde_json::to_string(&entries).wrap_err("failed to serialize search results")?;
+            println!("{json}");
+        } else {
+            for entry in &entries {
+                println!("* {} ({})", entry.attr_name, entry.version);
+                println!("  {}", entry.description);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn query(term: &str, limit: u64, channel: &str) -> Result<Vec<SearchEntry>> {
+    let endpoint = format!("https://search.nixos.org/backend/latest-*-{channel}/_search");
+
+    let response: serde_json::Value = ureq::post(&endpoint)
+        .set("Content-Type", "application/json")
+        .send_json(ureq::json!({
+            "query": {
+                "multi_match": {
+                    "query": term,
+                    "fields": ["package_attr_name", "package_pname", "package_description"],
+                },
+            },
+            "size": limit,
+        }))
+        .wrap_err("failed to query search.nixos.org")?
+        .into_json()
+        .wrap_err("failed to parse search.nixos.org response")?;
+
+    let hits = response["hits"]["hits"].as_array().cloned().unwrap_or_default();
+
+    Ok(hits
+        .into_iter()
+        .map(|hit| {
+            let source = &hit["_source"];
+            SearchEntry {
+                attr_name: source["package_attr_name"].as_str().unwrap_or_default().to_owned(),
+                pname: source["package_pname"].as_str().unwrap_or_default().to_owned(),
+                version: source["package_pversion"].as_str().unwrap_or_default().to_owned(),
+                description: source["package_description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                channel: channel.to_owned(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_entry_serializes_to_json() {
+        let entry = SearchEntry {
+            attr_name: "wget".into(),
+            pname: "wget".into(),
+            version: "1.21".into(),
+            description: "A network downloader".into(),
+            channel: "nixos-unstable".into(),
+        };
+
+        let json = serde_json::to_string(&[entry]).unwrap();
+        assert!(json.contains("\"attr_name\":\"wget\""));
+        assert!(!json.contains('\n'), "json output must not include decorative formatting");
+    }
+}